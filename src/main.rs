@@ -3,7 +3,7 @@ use clap::Parser;
 use pulldown_cmark::{CodeBlockKind, Event, Parser as MarkdownParser, Tag};
 use regex::Regex;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
     fs::{self},
     io,
@@ -28,21 +28,56 @@ struct Cli {
     /// on-disk counterparts and reports differences.
     #[arg(long, short)]
     test: bool,
+
+    /// Prefix tangled chunks with `#line`-style directives so that
+    /// compiler/interpreter errors point back at the Markdown source
+    /// instead of the generated file.
+    #[arg(long)]
+    line_directives: bool,
+
+    /// Tangle to a temporary directory, execute every chunk marked `{run}`,
+    /// and diff its output against any `{expect=...}` block.
+    #[arg(long)]
+    run: bool,
+
+    /// In test mode, also fail if the output directory contains generated
+    /// files with no corresponding chunk in the literate source.
+    #[arg(long)]
+    strict: bool,
 }
 /// Processes a list of markdown files and builds an in-memory map of the
 /// files to be generated, without writing anything to disk.
 fn generate_output_map(
     paths: &[PathBuf],
     root_dir: Option<&PathBuf>,
+    line_directives: bool,
 ) -> HashMap<PathBuf, String> {
-    // First, we'll store all raw chunks in this vector
+    let all_chunks = extract_all_chunks(paths);
+    generate_output_map_from_chunks(&all_chunks, root_dir, line_directives)
+}
+/// Resolves the root directory chunks are tangled into: the explicit
+/// `--dir`, or the current working directory if none was given.
+fn resolve_base_dir(root_dir: Option<&PathBuf>) -> PathBuf {
+    root_dir.cloned().unwrap_or_else(|| env::current_dir().unwrap())
+}
+/// Reads every path and concatenates all of its chunks, in document order.
+fn extract_all_chunks(paths: &[PathBuf]) -> Vec<Chunk> {
     let mut all_chunks = Vec::new();
-    
+
     for path in paths.iter() {
         all_chunks.extend(extract_chunks(path.to_str().unwrap()));
     }
 
-
+    return all_chunks;
+}
+/// Expands already-extracted chunks into the in-memory file map. Split out
+/// from `generate_output_map` so that `--run` can tangle into a temporary
+/// directory while reusing the same chunks it inspects for `{run}`/`{expect}`.
+fn generate_output_map_from_chunks(
+    all_chunks: &[Chunk],
+    root_dir: Option<&PathBuf>,
+    line_directives: bool,
+) -> HashMap<PathBuf, String> {
     // Next, we create two data structures with all chunks
     // A map of all named chunks for easy lookup during expansion.
     let named_chunks_map = create_named_chunk_map(&all_chunks);
@@ -56,12 +91,12 @@ fn generate_output_map(
     let mut source_map: HashMap<PathBuf, String> = HashMap::new();
     
     // Define the base dir (or use default)
-    let base_dir = root_dir.cloned().unwrap_or_else(|| env::current_dir().unwrap());
+    let base_dir = resolve_base_dir(root_dir);
     
     // Expand all exportable chunks and collect their content into the output map.
     for chunk in exportable_chunks {
         // This is the where the recursive magic happens
-        let content = chunk.expand(&named_chunks_map);
+        let content = chunk.expand(&named_chunks_map, line_directives);
         // This is the path to the file where the chunk should be written.
         let file_path = base_dir.join(chunk.info.path.as_ref().unwrap());
         // Append the expanded content of the current chunk to the appropriate file's content in the map.
@@ -75,6 +110,11 @@ fn generate_output_map(
 struct Chunk {
     info: ChunkInfo,
     content: String,
+    /// Path of the Markdown file this chunk was extracted from, used to
+    /// build line directives that point back at the literate source.
+    file_path: String,
+    /// 1-based line number of the opening code fence in `file_path`.
+    start_line: usize,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -83,24 +123,38 @@ struct ChunkInfo {
     path: Option<String>,
     name: Option<String>,
     export: bool,
+    /// Marks an exportable chunk as executable under `--run`.
+    run: bool,
+    /// Tangle this chunk but skip executing it, even if `run` is set.
+    norun: bool,
+    /// Expect a non-zero exit status when this chunk is executed.
+    should_fail: bool,
+    /// Name of the chunk whose literal content is the expected stdout.
+    expect: Option<String>,
 }
 fn extract_chunks(file_path: &str) -> Vec<Chunk> {
     let mut chunks = Vec::new();
     let content = std::fs::read_to_string(file_path).unwrap();
-    let parser = MarkdownParser::new(&content);
+    // The offset iterator hands back the byte range of every event, which
+    // lets us recover the line number of each code fence for provenance.
+    let parser = MarkdownParser::new(&content).into_offset_iter();
     let mut in_chunk = false;
 
     // list of common language extensions (e.g., .py, .rs, .cpp)
     let lang_ext = language_extensions();
 
-    for event in parser {
+    for (event, range) in parser {
         match event {
             Event::Start(Tag::CodeBlock(kind)) => {
                 if let CodeBlockKind::Fenced(info_str) = kind {
                     if let Some(info) = parse_info_string(&info_str) {
+                        // Count newlines up to the fence to get a 1-based line number.
+                        let start_line = content[..range.start].matches('\n').count() + 1;
                         let mut chunk = Chunk {
                             info,
                             content: String::new(),
+                            file_path: file_path.to_string(),
+                            start_line,
                         };
                 
                         // For empty export directives, we generate a default path
@@ -172,6 +226,35 @@ fn language_extensions() -> HashMap<&'static str, &'static str> {
 
     return map;
 }
+/// Maps a chunk's language to the shell command used to execute it under
+/// `--run`. The `{file}` placeholder is substituted with the tangled file's
+/// path. Rust isn't listed here: it's compiled with `rustc` before running,
+/// so it's handled separately in `run_chunks`.
+fn language_commands() -> HashMap<&'static str, &'static str> {
+    let mut map = HashMap::new();
+
+    map.insert("python", "python3 {file}");
+    map.insert("javascript", "node {file}");
+    map.insert("ruby", "ruby {file}");
+    map.insert("bash", "bash {file}");
+    map.insert("php", "php {file}");
+    map.insert("perl", "perl {file}");
+    map.insert("lua", "lua {file}");
+    map.insert("r", "Rscript {file}");
+
+    return map;
+}
+/// Returns the line-directive comment for languages that support one, so
+/// that compiler/interpreter errors against tangled output point back at
+/// `file:line` in the originating Markdown. Languages with no such concept
+/// return `None` and are left untouched.
+fn line_directive(lang: &str, line: usize, file: &str) -> Option<String> {
+    match lang {
+        "c" | "cpp" | "objc" | "csharp" => Some(format!("#line {} \"{}\"\n", line, file)),
+        "assembly" => Some(format!("# {} \"{}\"\n", line, file)),
+        _ => None,
+    }
+}
 fn parse_info_string(info_string: &str) -> Option<ChunkInfo> {
     // First, capture the language and the rest of the attributes string.
     let lang_re = Regex::new(r"^\s*(?P<lang>\w+)\s*(?P<attrs>.*)$").unwrap();
@@ -185,6 +268,10 @@ fn parse_info_string(info_string: &str) -> Option<ChunkInfo> {
     let mut path = None;
     let mut name = None;
     let mut export = false;
+    let mut run = false;
+    let mut norun = false;
+    let mut should_fail = false;
+    let mut expect = None;
 
     // Iterate over all attribute matches found in the string.
     for attr_caps in attr_re.captures_iter(attrs_str) {
@@ -206,6 +293,21 @@ fn parse_info_string(info_string: &str) -> Option<ChunkInfo> {
                 }
                 // Note: {name} without a value is ignored.
             }
+            "run" => {
+                run = true;
+            }
+            "norun" => {
+                norun = true;
+            }
+            "should_fail" => {
+                should_fail = true;
+            }
+            "expect" => {
+                if let Some(val_match) = value {
+                    expect = Some(val_match.as_str().to_string());
+                }
+                // Note: {expect} without a value is ignored.
+            }
             _ => {} // Ignore unknown attributes
         }
 
@@ -220,6 +322,10 @@ fn parse_info_string(info_string: &str) -> Option<ChunkInfo> {
         path: path,
         name: name,
         export: export,
+        run: run,
+        norun: norun,
+        should_fail: should_fail,
+        expect: expect,
     });
 }
 fn create_named_chunk_map(chunks: &[Chunk]) -> HashMap<String, Vec<&Chunk>> {
@@ -235,16 +341,22 @@ fn create_named_chunk_map(chunks: &[Chunk]) -> HashMap<String, Vec<&Chunk>> {
 impl Chunk {
     /// Public method to start the expansion process.
     /// It initializes the tracking stack for circular dependency checks.
-    pub fn expand(&self, named_chunks: &HashMap<String, Vec<&Chunk>>) -> String {
+    pub fn expand(&self, named_chunks: &HashMap<String, Vec<&Chunk>>, line_directives: bool) -> String {
         let mut expansion_stack = Vec::new();
-        self.expand_recursive(named_chunks, &mut expansion_stack)
+        self.expand_recursive(named_chunks, &mut expansion_stack, line_directives)
     }
 
     /// Recursively expands the content of this chunk by replacing `<<...>>` references.
+    ///
+    /// When `line_directives` is set, the expansion is prefixed with a directive
+    /// pointing at this chunk's position in its source file, and a fresh directive
+    /// is re-emitted after every `<<ref>>` expansion so the line counter resets to
+    /// the enclosing chunk's true position.
     fn expand_recursive(
         &self,
         named_chunks: &HashMap<String, Vec<&Chunk>>,
         expansion_stack: &mut Vec<String>,
+        line_directives: bool,
     ) -> String {
         // Check for circular dependencies.
         if let Some(name) = &self.info.name {
@@ -257,24 +369,32 @@ impl Chunk {
             }
             expansion_stack.push(name.clone());
         }
-    
-    
+
+
         // This will hold the final expanded chunk
         let mut final_content = String::new();
         // This regex matches lines with a named reference in the form <<...>>
         let include_re = Regex::new(r"^(?P<indent>\s*)<<(?P<name>[\w_.-]+)>>\s*$").unwrap();
-    
-        for line in self.content.lines() {
+
+        // The chunk's content starts on the line right after its opening fence.
+        if line_directives {
+            if let Some(directive) = line_directive(&self.info.lang, self.start_line + 1, &self.file_path) {
+                final_content.push_str(&directive);
+            }
+        }
+
+        for (idx, line) in self.content.lines().enumerate() {
             if let Some(caps) = include_re.captures(line) {
                 // This line contains a named reference.
                 let indent_str = caps.name("indent").unwrap().as_str();
                 let name_to_include = caps.name("name").unwrap().as_str();
-    
+
                 match named_chunks.get(name_to_include) {
                     Some(chunks_to_include) => {
                         for chunk in chunks_to_include {
                             // Recursively expand the included chunk.
-                            let expanded_include = chunk.expand_recursive(named_chunks, expansion_stack);
+                            let expanded_include =
+                                chunk.expand_recursive(named_chunks, expansion_stack, line_directives);
                             // Add the captured indentation to each line of the expanded content.
                             for expanded_line in expanded_include.lines() {
                                 final_content.push_str(indent_str);
@@ -283,28 +403,37 @@ impl Chunk {
                             }
                             final_content.push('\n');
                         }
+                        // Resume the enclosing chunk's own line numbering now that
+                        // the reference has been fully expanded.
+                        if line_directives {
+                            if let Some(directive) =
+                                line_directive(&self.info.lang, self.start_line + idx + 2, &self.file_path)
+                            {
+                                final_content.push_str(&directive);
+                            }
+                        }
                     }
                     None => {
                         // Handle missing chunk reference
                         panic!("ERROR: Chunk '{}' not found", name_to_include);
                     }
                 }
-    
+
             } else {
                 // This line doesn't, so add it as is.
                 final_content.push_str(line);
                 final_content.push('\n');
             }
         }
-    
+
         // Some post-process we will need to make circular checks work
         if let Some(name) = &self.info.name {
             if expansion_stack.last() == Some(name) {
                 expansion_stack.pop();
             }
         }
-    
-    
+
+
         return final_content;
     }
 
@@ -320,14 +449,19 @@ fn write_output_to_disk(output_map: &HashMap<PathBuf, String>) -> io::Result<()>
     Ok(())
 }
 /// Compares the in-memory file map with files on disk and reports differences.
-fn run_test_comparison(output_map: &HashMap<PathBuf, String>) -> bool {
+///
+/// Mismatches are reported as a line-level unified diff. With `strict`, files
+/// under `base_dir` that look generated (a known language extension) but
+/// have no corresponding chunk in `output_map` also fail the run.
+fn run_test_comparison(output_map: &HashMap<PathBuf, String>, base_dir: &Path, strict: bool) -> bool {
     let mut differences = Vec::new();
 
     for (path, generated_content) in output_map {
         match fs::read_to_string(path) {
             Ok(disk_content) => {
                 if &disk_content != generated_content {
-                    differences.push(format!("Content mismatch in {}", path.display()));
+                    let diff = diff_lines(&disk_content, generated_content).join("\n");
+                    differences.push(format!("Content mismatch in {}:\n{}", path.display(), diff));
                 }
             }
             Err(e) if e.kind() == io::ErrorKind::NotFound => {
@@ -340,8 +474,21 @@ fn run_test_comparison(output_map: &HashMap<PathBuf, String>) -> bool {
 
     }
 
-    // Also check for any files on disk that shouldn't be there (optional but good practice)
-    // For now, we'll stick to the core requirement.
+    // Files on disk that look generated but aren't in the output map anymore,
+    // e.g. left behind after a chunk was renamed or removed from the source.
+    let orphans = find_orphaned_files(output_map, base_dir);
+    if !orphans.is_empty() {
+        if strict {
+            for orphan in &orphans {
+                differences.push(format!("Orphaned generated file: {}", orphan.display()));
+            }
+        } else {
+            println!("⚠️  Found {} orphaned file(s) (pass --strict to fail on these):", orphans.len());
+            for orphan in &orphans {
+                println!("  - {}", orphan.display());
+            }
+        }
+    }
 
     if differences.is_empty() {
         println!("✅ All {} generated files are in sync with the disk.", output_map.len());
@@ -354,17 +501,277 @@ fn run_test_comparison(output_map: &HashMap<PathBuf, String>) -> bool {
         return false;
     }
 }
+/// Computes a line-level unified diff between `old` and `new`, returned as
+/// lines prefixed with `"  "` (shared), `"- "` (removed), or `"+ "` (added).
+///
+/// Builds the classic longest-common-subsequence table over both line
+/// vectors, then walks it to recover which lines were kept, dropped, or added.
+fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let (n, m) = (old_lines.len(), new_lines.len());
+
+    // lcs[i][j] holds the length of the LCS of old_lines[i..] and new_lines[j..].
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // Walk the table from (0, 0), following whichever neighbor preserves the
+    // LCS length to decide whether a line was kept, removed, or added.
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old_lines[i] == new_lines[j] {
+            diff.push(format!("  {}", old_lines[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(format!("- {}", old_lines[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+ {}", new_lines[j]));
+            j += 1;
+        }
+    }
+    while i < n {
+        diff.push(format!("- {}", old_lines[i]));
+        i += 1;
+    }
+    while j < m {
+        diff.push(format!("+ {}", new_lines[j]));
+        j += 1;
+    }
+
+    return diff;
+}
+/// Walks `base_dir` for files whose extension matches a known language but
+/// that have no corresponding entry in `output_map`, i.e. checked-in files
+/// the literate source no longer (or never did) account for.
+fn find_orphaned_files(output_map: &HashMap<PathBuf, String>, base_dir: &Path) -> Vec<PathBuf> {
+    let known_extensions: HashSet<&str> = language_extensions().values().copied().collect();
+    let mut orphans = Vec::new();
+
+    walk_dir(base_dir, &mut |path| {
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            if known_extensions.contains(ext) && !output_map.contains_key(path) {
+                orphans.push(path.to_path_buf());
+            }
+        }
+    });
+
+    return orphans;
+}
+/// Directory names that are never part of a literate source tree. Skipped
+/// during the orphan walk so VCS metadata and build output (which routinely
+/// contains generated `.rs`/`.c` files of their own) aren't misreported.
+fn excluded_dir_names() -> &'static [&'static str] {
+    &[".git", ".hg", ".svn", "target", "node_modules", ".venv", "venv"]
+}
+/// Recursively visits every file under `dir`, silently skipping entries that
+/// can't be read (e.g. permission errors, or `dir` itself not existing) and
+/// pruning conventional VCS/build directories (see `excluded_dir_names`).
+fn walk_dir(dir: &Path, visit: &mut impl FnMut(&Path)) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            let is_excluded = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| excluded_dir_names().contains(&name))
+                .unwrap_or(false);
+            if is_excluded {
+                continue;
+            }
+            walk_dir(&path, visit);
+        } else {
+            visit(&path);
+        }
+    }
+}
+
+
+/// Outcome of executing a single `{run}` chunk.
+struct RunReport {
+    file_path: PathBuf,
+    passed: bool,
+    message: String,
+}
+/// Executes every chunk marked `{run}` (and not `{norun}`) against the
+/// tangled output under `base_dir`, diffing captured stdout against any
+/// `{expect=...}` block and honoring `{should_fail}`.
+fn run_chunks(
+    all_chunks: &[Chunk],
+    named_chunks_map: &HashMap<String, Vec<&Chunk>>,
+    base_dir: &Path,
+) -> Vec<RunReport> {
+    let commands = language_commands();
+    let mut reports = Vec::new();
+
+    for chunk in all_chunks
+        .iter()
+        .filter(|chunk| chunk.info.export && chunk.info.run && !chunk.info.norun)
+    {
+        let file_path = base_dir.join(chunk.info.path.as_ref().unwrap());
+
+        let output = if chunk.info.lang == "rust" {
+            run_rust_chunk(&file_path)
+        } else {
+            match commands.get(chunk.info.lang.as_str()) {
+                Some(template) => run_shell_command(template, &file_path),
+                None => {
+                    reports.push(RunReport {
+                        file_path,
+                        passed: false,
+                        message: format!("no runner configured for language '{}'", chunk.info.lang),
+                    });
+                    continue;
+                }
+            }
+        };
+
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => {
+                reports.push(RunReport {
+                    file_path,
+                    passed: false,
+                    message: format!("failed to execute: {}", e),
+                });
+                continue;
+            }
+        };
+
+        let exit_ok = if chunk.info.should_fail {
+            !output.status.success()
+        } else {
+            output.status.success()
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (stdout_ok, mismatch) = match &chunk.info.expect {
+            Some(expect_name) => match named_chunks_map.get(expect_name).and_then(|chunks| chunks.first()) {
+                Some(expect_chunk) => {
+                    let expected = expect_chunk.content.trim_end();
+                    if stdout.trim_end() == expected {
+                        (true, String::new())
+                    } else {
+                        (
+                            false,
+                            format!(
+                                "output mismatch\n  expected: {:?}\n  actual:   {:?}",
+                                expected,
+                                stdout.trim_end()
+                            ),
+                        )
+                    }
+                }
+                // Mirrors expand_recursive's handling of an unresolved `<<name>>`:
+                // an unknown expect target is an error, not an empty expectation.
+                None => (false, format!("no such expect chunk '{}'", expect_name)),
+            },
+            None => (true, String::new()),
+        };
+
+        let passed = exit_ok && stdout_ok;
+        let message = if passed {
+            "ok".to_string()
+        } else if !exit_ok {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            format!("unexpected exit status {}: {}", output.status, stderr.trim())
+        } else {
+            mismatch
+        };
+
+        reports.push(RunReport { file_path, passed, message });
+    }
+
+    return reports;
+}
+/// Compiles a Rust chunk with `rustc` and runs the resulting binary.
+fn run_rust_chunk(file_path: &Path) -> io::Result<std::process::Output> {
+    let binary_path = file_path.with_extension("");
+    let compile = std::process::Command::new("rustc")
+        .arg(file_path)
+        .arg("-o")
+        .arg(&binary_path)
+        .output()?;
 
+    if !compile.status.success() {
+        return Ok(compile);
+    }
+
+    return std::process::Command::new(&binary_path).output();
+}
+/// Runs a whitespace-separated command template such as `python3 {file}`,
+/// substituting `{file}` into each argument token *before* building the
+/// `Command` so a file path containing spaces is still passed as one argv
+/// entry rather than being split apart.
+fn run_shell_command(template: &str, file_path: &Path) -> io::Result<std::process::Output> {
+    let file_str = file_path.to_string_lossy();
+    let mut parts = template.split_whitespace();
+    let program = parts.next().unwrap_or(template).replace("{file}", &file_str);
+    let args: Vec<String> = parts.map(|part| part.replace("{file}", &file_str)).collect();
+    return std::process::Command::new(program).args(args).output();
+}
 
 fn main() {
     let args = Cli::parse();
 
+    if args.run {
+        // Tangle to a throwaway directory and execute the `{run}` chunks
+        // instead of touching the user's own output tree.
+        let all_chunks = extract_all_chunks(&args.files);
+        let named_chunks_map = create_named_chunk_map(&all_chunks);
+        let run_dir = env::temp_dir().join(format!("illiterate-run-{}", std::process::id()));
+        let output_map = generate_output_map_from_chunks(&all_chunks, Some(&run_dir), args.line_directives);
+
+        let exit_code = match write_output_to_disk(&output_map) {
+            Ok(_) => {
+                let reports = run_chunks(&all_chunks, &named_chunks_map, &run_dir);
+                let mut any_failed = false;
+
+                for report in &reports {
+                    if report.passed {
+                        println!("✅ {}", report.file_path.display());
+                    } else {
+                        any_failed = true;
+                        println!("❌ {}: {}", report.file_path.display(), report.message);
+                    }
+                }
+
+                if any_failed { 1 } else { 0 }
+            }
+            Err(e) => {
+                eprintln!("🔥 Error tangling to {}: {}", run_dir.display(), e);
+                1
+            }
+        };
+
+        // Scratch directory: clean it up regardless of how the run went.
+        let _ = fs::remove_dir_all(&run_dir);
+
+        std::process::exit(exit_code);
+    }
+
     // 1. Generate the complete output in memory
-    let output_map = generate_output_map(&args.files, args.dir.as_ref());
+    let output_map = generate_output_map(&args.files, args.dir.as_ref(), args.line_directives);
 
     if args.test {
         // 2a. Run the test logic
-        if !run_test_comparison(&output_map) {
+        let base_dir = resolve_base_dir(args.dir.as_ref());
+        if !run_test_comparison(&output_map, &base_dir, args.strict) {
             // Exit with a non-zero code to indicate test failure
             std::process::exit(1);
         }
@@ -421,6 +828,38 @@ mod tests {
         assert!(chunks.len() == 2);
     }
 
+    #[test]
+    fn test_start_line_tracking() {
+        let chunks = extract_chunks("tests/start_line.md");
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].start_line, 5);
+        assert_eq!(chunks[0].file_path, "tests/start_line.md");
+    }
+
+    #[test]
+    fn test_line_directives_resume_after_reference() {
+        let all_chunks = extract_chunks("tests/line_directives.md");
+        let named_chunks_map = create_named_chunk_map(&all_chunks);
+        let main_chunk = all_chunks.iter().find(|chunk| chunk.info.export).unwrap();
+
+        let expanded = main_chunk.expand(&named_chunks_map, true);
+
+        // `int main() {` is line 8, the nested `<<greeting>>` chunk starts at
+        // line 4, and after it returns the enclosing chunk resumes numbering
+        // at line 10 (`return 0;`), not line 9 where the reference sat.
+        let expected = "#line 8 \"tests/line_directives.md\"\n\
+int main() {\n\
+#line 4 \"tests/line_directives.md\"\n\
+printf(\"hello\");\n\
+\n\
+#line 10 \"tests/line_directives.md\"\n\
+return 0;\n\
+}\n";
+
+        assert_eq!(expanded, expected);
+    }
+
     #[test]
     fn test_full_string_parsing() {
         let info = "rust {export=src/main.rs} {name=chunk_1}";
@@ -429,6 +868,10 @@ mod tests {
             path: Some("src/main.rs".to_string()),
             name: Some("chunk_1".to_string()),
             export: true,
+            run: false,
+            norun: false,
+            should_fail: false,
+            expect: None,
         };
         assert_eq!(parse_info_string(info), Some(expected));
     }
@@ -441,6 +884,10 @@ mod tests {
             path: None,
             name: Some("hello_world".to_string()),
             export: false,
+            run: false,
+            norun: false,
+            should_fail: false,
+            expect: None,
         };
         assert_eq!(parse_info_string(info), Some(expected));
     }
@@ -453,6 +900,10 @@ mod tests {
             path: Some("src/main.rs".to_string()),
             name: Some("chunk_1".to_string()),
             export: true,
+            run: false,
+            norun: false,
+            should_fail: false,
+            expect: None,
         };
         assert_eq!(parse_info_string(info), Some(expected));
     }
@@ -471,6 +922,10 @@ mod tests {
             path: Some("app.js".to_string()),
             name: None,
             export: true,
+            run: false,
+            norun: false,
+            should_fail: false,
+            expect: None,
         };
         assert_eq!(parse_info_string(info), Some(expected));
     }
@@ -483,6 +938,10 @@ mod tests {
             path: None,
             name: None,
             export: true,
+            run: false,
+            norun: false,
+            should_fail: false,
+            expect: None,
         };
         assert_eq!(parse_info_string(info), Some(expected));
     }
@@ -495,6 +954,10 @@ mod tests {
             path: None,
             name: Some("my_frag".to_string()),
             export: true,
+            run: false,
+            norun: false,
+            should_fail: false,
+            expect: None,
         };
         assert_eq!(parse_info_string(info), Some(expected));
     }
@@ -507,6 +970,10 @@ mod tests {
             path: None,
             name: Some("my_fragment".to_string()),
             export: false,
+            run: false,
+            norun: false,
+            should_fail: false,
+            expect: None,
         };
         assert_eq!(parse_info_string(info), Some(expected));
     }
@@ -519,6 +986,10 @@ mod tests {
             path: Some("run.sh".to_string()),
             name: None,
             export: true,
+            run: false,
+            norun: false,
+            should_fail: false,
+            expect: None,
         };
         assert_eq!(parse_info_string(info), Some(expected));
     }
@@ -535,6 +1006,182 @@ mod tests {
         assert_eq!(parse_info_string(info), None);
     }
 
+    #[test]
+    fn test_run_and_expect() {
+        let info = "python {export=hello.py} {run} {expect=hello_output}";
+        let expected = ChunkInfo {
+            lang: "python".to_string(),
+            path: Some("hello.py".to_string()),
+            name: None,
+            export: true,
+            run: true,
+            norun: false,
+            should_fail: false,
+            expect: Some("hello_output".to_string()),
+        };
+        assert_eq!(parse_info_string(info), Some(expected));
+    }
+
+    #[test]
+    fn test_run_and_norun_both_parsed() {
+        let info = "rust {export=demo.rs} {run} {norun}";
+        let expected = ChunkInfo {
+            lang: "rust".to_string(),
+            path: Some("demo.rs".to_string()),
+            name: None,
+            export: true,
+            run: true,
+            norun: true,
+            should_fail: false,
+            expect: None,
+        };
+        assert_eq!(parse_info_string(info), Some(expected));
+    }
+
+    #[test]
+    fn test_should_fail() {
+        let info = "bash {export=broken.sh} {run} {should_fail}";
+        let expected = ChunkInfo {
+            lang: "bash".to_string(),
+            path: Some("broken.sh".to_string()),
+            name: None,
+            export: true,
+            run: true,
+            norun: false,
+            should_fail: true,
+            expect: None,
+        };
+        assert_eq!(parse_info_string(info), Some(expected));
+    }
+
+    #[test]
+    fn test_diff_lines_no_changes() {
+        let diff = diff_lines("a\nb\nc\n", "a\nb\nc\n");
+        assert_eq!(diff, vec!["  a", "  b", "  c"]);
+    }
+
+    #[test]
+    fn test_diff_lines_pure_insert() {
+        let diff = diff_lines("a\nc\n", "a\nb\nc\n");
+        assert_eq!(diff, vec!["  a", "+ b", "  c"]);
+    }
+
+    #[test]
+    fn test_diff_lines_pure_delete() {
+        let diff = diff_lines("a\nb\nc\n", "a\nc\n");
+        assert_eq!(diff, vec!["  a", "- b", "  c"]);
+    }
+
+    #[test]
+    fn test_diff_lines_interleaved_edit() {
+        let diff = diff_lines("a\nb\nc\n", "a\nx\nc\n");
+        assert_eq!(diff, vec!["  a", "- b", "+ x", "  c"]);
+    }
+
+    #[test]
+    fn test_find_orphaned_files() {
+        let dir = env::temp_dir().join("illiterate_test_orphans");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let known_path = dir.join("known.rs");
+        let orphan_path = dir.join("orphan.rs");
+        fs::write(&known_path, "// known\n").unwrap();
+        fs::write(&orphan_path, "// orphan\n").unwrap();
+
+        let mut output_map = HashMap::new();
+        output_map.insert(known_path.clone(), "// known\n".to_string());
+
+        let orphans = find_orphaned_files(&output_map, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(orphans, vec![orphan_path]);
+    }
+
+    #[test]
+    fn test_find_orphaned_files_skips_excluded_dirs() {
+        let dir = env::temp_dir().join("illiterate_test_orphans_excluded");
+        let _ = fs::remove_dir_all(&dir);
+        let target_dir = dir.join("target");
+        fs::create_dir_all(&target_dir).unwrap();
+        fs::write(target_dir.join("build.rs"), "// generated\n").unwrap();
+
+        let output_map = HashMap::new();
+        let orphans = find_orphaned_files(&output_map, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(orphans.is_empty());
+    }
+
+    #[test]
+    fn test_run_test_comparison_strict_gates_orphans() {
+        let dir = env::temp_dir().join("illiterate_test_strict");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("orphan.py"), "# orphan\n").unwrap();
+
+        let output_map: HashMap<PathBuf, String> = HashMap::new();
+
+        let lenient = run_test_comparison(&output_map, &dir, false);
+        let strict = run_test_comparison(&output_map, &dir, true);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(lenient);
+        assert!(!strict);
+    }
+
+    #[test]
+    fn test_run_shell_command_handles_spaces_in_path() {
+        let dir = env::temp_dir().join("illiterate_test_run_shell_spaces");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let script_path = dir.join("say hello.sh");
+        fs::write(&script_path, "echo hello\n").unwrap();
+
+        let output = run_shell_command("bash {file}", &script_path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let output = output.unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), "hello");
+    }
+
+    #[test]
+    fn test_run_chunks_handles_path_with_space() {
+        let dir = env::temp_dir().join("illiterate_test_run_chunks_spaces");
+        let _ = fs::remove_dir_all(&dir);
+
+        let all_chunks = extract_chunks("tests/run_with_space.md");
+        let named_chunks_map = create_named_chunk_map(&all_chunks);
+        let output_map = generate_output_map_from_chunks(&all_chunks, Some(&dir), false);
+        write_output_to_disk(&output_map).unwrap();
+
+        let reports = run_chunks(&all_chunks, &named_chunks_map, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].passed, "unexpected failure: {}", reports[0].message);
+    }
+
+    #[test]
+    fn test_run_chunks_reports_missing_expect_chunk() {
+        let dir = env::temp_dir().join("illiterate_test_run_chunks_missing_expect");
+        let _ = fs::remove_dir_all(&dir);
+
+        let all_chunks = extract_chunks("tests/missing_expect.md");
+        let named_chunks_map = create_named_chunk_map(&all_chunks);
+        let output_map = generate_output_map_from_chunks(&all_chunks, Some(&dir), false);
+        write_output_to_disk(&output_map).unwrap();
+
+        let reports = run_chunks(&all_chunks, &named_chunks_map, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reports.len(), 1);
+        assert!(!reports[0].passed);
+        assert_eq!(reports[0].message, "no such expect chunk 'nonexistent'");
+    }
+
     // ERROR: Chunk 'tests_build_chunk_map' not found
     // ERROR: Chunk 'tests_expand_chunk' not found
 }